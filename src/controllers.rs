@@ -1,8 +1,46 @@
+use async_trait::async_trait;
 use axum::extract::multipart::Field;
 use fs2::FileExt;
-use tokio::{fs::File, io::AsyncWriteExt, sync::Mutex};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, pin::Pin, time::Duration};
+use tokio::{
+    fs::File,
+    io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+    sync::Mutex,
+};
 
-use crate::routes::FileDownloadQuery;
+use crate::error::{ApplicationError, ApplicationResult, ErrorKind};
+
+/// Sniffs `bytes` and rejects the upload if its content type isn't allowed,
+/// or if `filename`'s extension disagrees with the sniffed content.
+fn validate_upload(filename: &str, bytes: &[u8]) -> ApplicationResult<()> {
+    let sniffed = crate::validate::sniff(bytes);
+
+    if !crate::validate::is_allowed(
+        sniffed,
+        &crate::ENVIRONMENT.allowed_types,
+        &crate::ENVIRONMENT.denied_types,
+    ) {
+        return Err(ApplicationError {
+            source: anyhow::anyhow!("sniffed content type {sniffed:?} is not allowed"),
+            kind: ErrorKind::FileUpload,
+        });
+    }
+
+    if let Some(mime) = sniffed {
+        if !crate::validate::extension_matches(filename, mime) {
+            return Err(ApplicationError {
+                source: anyhow::anyhow!(
+                    "extension of '{filename}' disagrees with sniffed content type {mime}"
+                ),
+                kind: ErrorKind::FileUpload,
+            });
+        }
+    }
+
+    Ok(())
+}
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct Filedata {
@@ -17,16 +55,109 @@ impl From<tokio::fs::DirEntry> for Filedata {
     }
 }
 
+/// A boxed reader handed back by [`Store::open`], so callers can stream a
+/// download without caring whether the bytes came from disk or a bucket.
+pub type StoreReader = Pin<Box<dyn AsyncRead + Send>>;
+
+/// An inclusive byte range, resolved to concrete `start`/`end` offsets against
+/// a known total length (see [`Store::metadata`]).
+#[derive(Debug, Clone, Copy)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+/// Per-upload expiry settings: a TTL and/or a maximum number of downloads
+/// before the file is torn down. `Default` means "keep forever", matching
+/// the pre-existing behavior.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UploadOptions {
+    pub expires_in: Option<Duration>,
+    pub max_downloads: Option<u32>,
+}
+
+/// Outcome of [`Store::open`]: the file may be missing outright, or it may
+/// have existed but expired / run out of downloads since the caller last
+/// saw it.
+pub enum OpenOutcome {
+    NotFound,
+    Gone,
+    Found(StoreReader),
+}
+
+/// Abstracts over where uploaded file bytes actually live. [`FileStore`] keeps
+/// the original directory-backed behavior; [`ObjectStore`] talks to an
+/// S3-compatible bucket instead. Routes only ever see `Arc<dyn Store>`.
+#[async_trait]
+pub trait Store: Send + Sync {
+    async fn save(&self, field: Field<'_>, options: UploadOptions) -> ApplicationResult<Filedata>;
+    /// Total size of the stored file in bytes, or `None` if it doesn't exist.
+    async fn metadata(&self, name: &str) -> ApplicationResult<Option<u64>>;
+    /// Open the file for reading, optionally restricted to `range`. Also
+    /// consumes one download against the file's remaining-count limit, if it
+    /// has one, but only for a full (non-range) request — a `Range` request
+    /// is part of a larger transfer (probing, resuming, pausing) and
+    /// shouldn't burn the limit on its own.
+    async fn open(&self, name: &str, range: Option<ByteRange>) -> ApplicationResult<OpenOutcome>;
+    async fn list(&self) -> ApplicationResult<Vec<Filedata>>;
+    async fn info(&self) -> String;
+}
+
+fn now_unix() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn is_safe_filename(name: &str) -> bool {
+    let path = std::path::Path::new(name);
+    let has_traversal = path.components().any(|c| {
+        matches!(
+            c,
+            std::path::Component::ParentDir
+                | std::path::Component::RootDir
+                | std::path::Component::Prefix(_)
+        )
+    });
+
+    !name.is_empty() && !has_traversal
+}
+
+/// A single entry in [`FileStore`]'s expiry index, tracking how much longer
+/// an ephemeral upload is allowed to live.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct UploadRecord {
+    expires_at: Option<u64>,
+    remaining_downloads: Option<u32>,
+}
+
+impl UploadRecord {
+    fn is_live(&self) -> bool {
+        let expired = self.expires_at.is_some_and(|t| t <= now_unix());
+        let exhausted = self.remaining_downloads == Some(0);
+        !expired && !exhausted
+    }
+}
+
+const REAP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Size of each part in an [`ObjectStore`] multipart upload; bounds how much
+/// of an in-flight upload is ever held in memory at once.
+const S3_PART_SIZE: usize = 8 * 1024 * 1024;
+
 #[derive(Debug)]
-pub struct FileUploader {
+pub struct FileStore {
     #[allow(dead_code)]
     lock_file: std::fs::File,
     folder_path: std::path::PathBuf,
-    upload_count: tokio::sync::Mutex<u64>,
+    upload_count: Mutex<u64>,
+    index: Mutex<HashMap<String, UploadRecord>>,
+    use_io_uring: bool,
 }
 
-impl FileUploader {
-    fn new(folder_path: std::path::PathBuf) -> anyhow::Result<Self> {
+impl FileStore {
+    fn new(folder_path: std::path::PathBuf, io_uring_requested: bool) -> anyhow::Result<Self> {
         std::fs::create_dir_all(&folder_path)?;
 
         let mut lockfile_path = folder_path.clone();
@@ -39,31 +170,495 @@ impl FileUploader {
             .open(lockfile_path)?;
         lock_file.try_lock_exclusive()?;
 
+        let mut index_path = folder_path.clone();
+        index_path.push(".index.json");
+        let index = std::fs::read_to_string(&index_path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+
+        let use_io_uring = io_uring_requested && crate::io_uring::is_available();
+        if io_uring_requested && !use_io_uring {
+            tracing::warn!("--io-uring was requested but is unavailable; falling back to tokio::fs");
+        }
+
         Ok(Self {
             lock_file,
             folder_path,
             upload_count: Mutex::new(0),
+            index: Mutex::new(index),
+            use_io_uring,
         })
     }
 
-    pub fn init(folder_path: &str) -> anyhow::Result<Self> {
+    pub fn init(folder_path: &str, io_uring_requested: bool) -> anyhow::Result<Self> {
         let as_path = std::path::PathBuf::from(folder_path);
-        Self::new(as_path)
+        Self::new(as_path, io_uring_requested)
+    }
+
+    fn index_path(&self) -> std::path::PathBuf {
+        let mut path = self.folder_path.clone();
+        path.push(".index.json");
+        path
+    }
+
+    async fn persist_index(&self) -> anyhow::Result<()> {
+        let index = self.index.lock().await;
+        let data = serde_json::to_vec_pretty(&*index)?;
+        tokio::fs::write(self.index_path(), data).await?;
+        Ok(())
+    }
+
+    /// Spawns a background task that periodically deletes expired or
+    /// download-exhausted uploads, so the folder doesn't grow forever.
+    pub fn spawn_reaper(self: std::sync::Arc<Self>) {
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(REAP_INTERVAL).await;
+                if let Err(e) = self.reap_expired().await {
+                    tracing::warn!("Reaper sweep failed: {e}");
+                }
+            }
+        });
+    }
+
+    async fn reap_expired(&self) -> anyhow::Result<()> {
+        let expired: Vec<String> = {
+            let index = self.index.lock().await;
+            index
+                .iter()
+                .filter(|(_, record)| !record.is_live())
+                .map(|(name, _)| name.clone())
+                .collect()
+        };
+
+        for name in &expired {
+            let mut file_path = self.folder_path.clone();
+            file_path.push(name);
+            let _ = tokio::fs::remove_file(file_path).await;
+        }
+
+        if !expired.is_empty() {
+            let mut index = self.index.lock().await;
+            for name in &expired {
+                index.remove(name);
+            }
+            drop(index);
+            self.persist_index().await?;
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self, field))]
+    async fn write_file(&self, mut field: Field<'_>, file_id: u64) -> ApplicationResult<Filedata> {
+        let default_filename = format!("file_upload_{}", file_id);
+        let raw_filename = field.file_name().unwrap_or(&default_filename);
+        let safe_name = std::path::Path::new(raw_filename)
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or(default_filename);
+
+        let mut file_path = self.folder_path.clone();
+        file_path.push(&safe_name);
+
+        if self.use_io_uring {
+            // The target file isn't created until `validate_upload` passes,
+            // so a rejected upload never leaves a zero-byte file behind.
+            let mut sniff_buffer: Vec<u8> = Vec::with_capacity(crate::validate::SNIFF_LEN);
+            let mut writer: Option<(tokio::sync::mpsc::Sender<Vec<u8>>, _)> = None;
+
+            let validate_result: ApplicationResult<()> = async {
+                while let Some(chunk) = field.chunk().await? {
+                    if let Some((tx, _)) = writer.as_ref() {
+                        let _ = tx.send(chunk.to_vec()).await;
+                        continue;
+                    }
+
+                    sniff_buffer.extend_from_slice(&chunk);
+                    if sniff_buffer.len() >= crate::validate::SNIFF_LEN {
+                        validate_upload(&safe_name, &sniff_buffer)?;
+                        let (tx, rx) = tokio::sync::mpsc::channel::<Vec<u8>>(4);
+                        let task = tokio::spawn(crate::io_uring::write_stream(file_path.clone(), rx));
+                        let _ = tx.send(std::mem::take(&mut sniff_buffer)).await;
+                        writer = Some((tx, task));
+                    }
+                }
+
+                if writer.is_none() {
+                    validate_upload(&safe_name, &sniff_buffer)?;
+                    let (tx, rx) = tokio::sync::mpsc::channel::<Vec<u8>>(4);
+                    let task = tokio::spawn(crate::io_uring::write_stream(file_path.clone(), rx));
+                    let _ = tx.send(std::mem::take(&mut sniff_buffer)).await;
+                    writer = Some((tx, task));
+                }
+
+                Ok(())
+            }
+            .await;
+
+            let write_result = match writer {
+                Some((tx, task)) => {
+                    drop(tx);
+                    task.await
+                        .map_err(|e| anyhow::anyhow!("io_uring write task panicked: {e}"))?
+                }
+                None => Ok(()),
+            };
+
+            validate_result?;
+            write_result?;
+
+            return Ok(Filedata {
+                filename: safe_name,
+            });
+        }
+
+        let mut sniff_buffer: Vec<u8> = Vec::with_capacity(crate::validate::SNIFF_LEN);
+        let mut file_handle: Option<tokio::fs::File> = None;
+
+        while let Some(chunk) = field.chunk().await? {
+            if let Some(handle) = file_handle.as_mut() {
+                handle.write_all(&chunk).await?;
+                continue;
+            }
+
+            sniff_buffer.extend_from_slice(&chunk);
+            if sniff_buffer.len() >= crate::validate::SNIFF_LEN {
+                validate_upload(&safe_name, &sniff_buffer)?;
+                let mut handle = tokio::fs::File::create(&file_path).await?;
+                handle.write_all(&sniff_buffer).await?;
+                file_handle = Some(handle);
+            }
+        }
+
+        let mut file_handle = match file_handle {
+            Some(handle) => handle,
+            None => {
+                validate_upload(&safe_name, &sniff_buffer)?;
+                let mut handle = tokio::fs::File::create(&file_path).await?;
+                handle.write_all(&sniff_buffer).await?;
+                handle
+            }
+        };
+
+        file_handle.flush().await?;
+        Ok(Filedata {
+            filename: safe_name,
+        })
+    }
+}
+
+#[async_trait]
+impl Store for FileStore {
+    #[tracing::instrument(skip(self, field))]
+    async fn save(&self, field: Field<'_>, options: UploadOptions) -> ApplicationResult<Filedata> {
+        let file_id = {
+            let mut count = self.upload_count.lock().await;
+
+            let id = *count;
+
+            *count = count
+                .checked_add(1)
+                .ok_or_else(|| anyhow::anyhow!("Upload counter overflow"))?;
+
+            id
+        };
+        let file_data = self.write_file(field, file_id).await?;
+
+        if options.expires_in.is_some() || options.max_downloads.is_some() {
+            let record = UploadRecord {
+                expires_at: options
+                    .expires_in
+                    .map(|d| now_unix().saturating_add(d.as_secs())),
+                remaining_downloads: options.max_downloads,
+            };
+            self.index
+                .lock()
+                .await
+                .insert(file_data.filename.clone(), record);
+            self.persist_index().await?;
+        }
+
+        Ok(file_data)
     }
 
-    pub async fn print_info(&self) {
-        println!("{}", self.get_info().await);
+    async fn metadata(&self, name: &str) -> ApplicationResult<Option<u64>> {
+        if !is_safe_filename(name) {
+            return Err(ApplicationError {
+                source: anyhow::anyhow!("'{name}' contains path traversal components"),
+                kind: ErrorKind::InvalidFilename,
+            });
+        }
+
+        let mut file_path = self.folder_path.clone();
+        file_path.push(name);
+        match tokio::fs::metadata(file_path).await {
+            Ok(meta) => Ok(Some(meta.len())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
     }
 
-    pub async fn get_info(&self) -> String {
+    async fn open(&self, name: &str, range: Option<ByteRange>) -> ApplicationResult<OpenOutcome> {
+        if !is_safe_filename(name) {
+            return Err(ApplicationError {
+                source: anyhow::anyhow!("'{name}' contains path traversal components"),
+                kind: ErrorKind::InvalidFilename,
+            });
+        }
+
+        let decremented = {
+            let mut index = self.index.lock().await;
+            match index.get_mut(name) {
+                Some(record) if !record.is_live() => {
+                    index.remove(name);
+                    drop(index);
+                    let mut file_path = self.folder_path.clone();
+                    file_path.push(name);
+                    let _ = tokio::fs::remove_file(file_path).await;
+                    self.persist_index().await?;
+                    return Ok(OpenOutcome::Gone);
+                }
+                Some(record) if range.is_none() => {
+                    if let Some(remaining) = record.remaining_downloads.as_mut() {
+                        *remaining = remaining.saturating_sub(1);
+                        true
+                    } else {
+                        false
+                    }
+                }
+                Some(_) | None => false,
+            }
+        };
+        if decremented {
+            self.persist_index().await?;
+        }
+
+        let mut file_path = self.folder_path.clone();
+        file_path.push(name);
+
+        if self.use_io_uring {
+            let total_len = match tokio::fs::metadata(&file_path).await {
+                Ok(meta) => meta.len(),
+                Err(_) => return Ok(OpenOutcome::NotFound),
+            };
+            let (start, len) = match range {
+                Some(r) => (r.start, r.end.saturating_sub(r.start).saturating_add(1)),
+                None => (0, total_len),
+            };
+            return Ok(OpenOutcome::Found(crate::io_uring::read_range(
+                file_path, start, len,
+            )));
+        }
+
+        let mut file = match File::open(file_path).await {
+            Ok(f) => f,
+            Err(_) => return Ok(OpenOutcome::NotFound),
+        };
+
+        let Some(range) = range else {
+            return Ok(OpenOutcome::Found(Box::pin(file)));
+        };
+
+        file.seek(std::io::SeekFrom::Start(range.start)).await?;
+        let take_len = range.end.saturating_sub(range.start).saturating_add(1);
+        Ok(OpenOutcome::Found(Box::pin(file.take(take_len))))
+    }
+
+    async fn list(&self) -> ApplicationResult<Vec<Filedata>> {
+        let mut file_reader = tokio::fs::read_dir(&self.folder_path).await?;
+        let mut files = vec![];
+
+        while let Some(file) = file_reader.next_entry().await? {
+            if file.file_type().await?.is_dir() {
+                continue;
+            }
+            if file.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            files.push(Filedata::from(file));
+        }
+
+        Ok(files)
+    }
+
+    async fn info(&self) -> String {
         format!(
             "Uploaded {} files to '{}'",
             self.upload_count.lock().await,
             self.folder_path.display()
         )
     }
+}
+
+/// Stores uploads as objects in an S3-compatible bucket instead of a local
+/// folder. Keys are namespaced under `prefix`, mirroring how [`FileStore`]
+/// namespaces files under its folder.
+pub struct ObjectStore {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+    prefix: String,
+    upload_count: Mutex<u64>,
+}
+
+impl ObjectStore {
+    pub async fn init(endpoint: String, bucket: String, prefix: String) -> anyhow::Result<Self> {
+        let config = aws_config::from_env().endpoint_url(endpoint).load().await;
+        let client = aws_sdk_s3::Client::new(&config);
+
+        Ok(Self {
+            client,
+            bucket,
+            prefix,
+            upload_count: Mutex::new(0),
+        })
+    }
+
+    fn key_for(&self, filename: &str) -> String {
+        if self.prefix.is_empty() {
+            filename.to_string()
+        } else {
+            format!("{}/{}", self.prefix.trim_end_matches('/'), filename)
+        }
+    }
+
+    async fn delete(&self, name: &str) -> anyhow::Result<()> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(self.key_for(name))
+            .send()
+            .await?;
+        Ok(())
+    }
 
-    pub async fn upload_file(&self, field: Field<'_>) -> anyhow::Result<Filedata> {
+    /// Returns the in-progress multipart upload id for `key`, creating one on
+    /// S3 first if `upload_id` hasn't been populated yet.
+    async fn ensure_multipart_upload(
+        &self,
+        key: &str,
+        upload_id: &mut Option<String>,
+    ) -> anyhow::Result<String> {
+        if let Some(id) = upload_id {
+            return Ok(id.clone());
+        }
+
+        let created = self
+            .client
+            .create_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await?;
+        let id = created
+            .upload_id()
+            .ok_or_else(|| anyhow::anyhow!("S3 did not return an upload id"))?
+            .to_string();
+        *upload_id = Some(id.clone());
+        Ok(id)
+    }
+
+    async fn upload_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_index: usize,
+        body: Vec<u8>,
+    ) -> anyhow::Result<aws_sdk_s3::types::CompletedPart> {
+        let part_number = i32::try_from(part_index)
+            .map_err(|_| anyhow::anyhow!("too many upload parts"))?
+            .saturating_add(1);
+
+        let uploaded = self
+            .client
+            .upload_part()
+            .bucket(&self.bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .part_number(part_number)
+            .body(body.into())
+            .send()
+            .await?;
+
+        Ok(aws_sdk_s3::types::CompletedPart::builder()
+            .part_number(part_number)
+            .set_e_tag(uploaded.e_tag().map(str::to_string))
+            .build())
+    }
+
+    /// Spawns a background task that periodically deletes expired or
+    /// download-exhausted objects, mirroring [`FileStore::spawn_reaper`] so
+    /// the S3 backend self-cleans too instead of only reclaiming lazily on
+    /// access.
+    pub fn spawn_reaper(self: std::sync::Arc<Self>) {
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(REAP_INTERVAL).await;
+                if let Err(e) = self.reap_expired().await {
+                    tracing::warn!("Object store reaper sweep failed: {e}");
+                }
+            }
+        });
+    }
+
+    async fn reap_expired(&self) -> anyhow::Result<()> {
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(&self.prefix)
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            let page = page?;
+            for object in page.contents() {
+                let Some(key) = object.key() else {
+                    continue;
+                };
+
+                let output = match self.client.head_object().bucket(&self.bucket).key(key).send().await
+                {
+                    Ok(output) => output,
+                    Err(aws_sdk_s3::error::SdkError::ServiceError(e))
+                        if e.err().is_not_found() =>
+                    {
+                        continue;
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+
+                let record = UploadRecord {
+                    expires_at: output
+                        .metadata()
+                        .and_then(|m| m.get("expires-at"))
+                        .and_then(|v| v.parse().ok()),
+                    remaining_downloads: output
+                        .metadata()
+                        .and_then(|m| m.get("remaining-downloads"))
+                        .and_then(|v| v.parse().ok()),
+                };
+
+                if !record.is_live() {
+                    self.client
+                        .delete_object()
+                        .bucket(&self.bucket)
+                        .key(key)
+                        .send()
+                        .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Store for ObjectStore {
+    #[tracing::instrument(skip(self, field))]
+    async fn save(&self, mut field: Field<'_>, options: UploadOptions) -> ApplicationResult<Filedata> {
         let file_id = {
             let mut count = self.upload_count.lock().await;
 
@@ -75,67 +670,340 @@ impl FileUploader {
 
             id
         };
-        self.write_file(field, file_id).await
-    }
 
-    pub async fn write_file(&self, mut field: Field<'_>, file_id: u64) -> anyhow::Result<Filedata> {
         let default_filename = format!("file_upload_{}", file_id);
         let raw_filename = field.file_name().unwrap_or(&default_filename);
         let safe_name = std::path::Path::new(raw_filename)
             .file_name()
             .map(|s| s.to_string_lossy().to_string())
             .unwrap_or(default_filename);
+        let key = self.key_for(&safe_name);
 
-        let mut file_path = self.folder_path.clone();
-        file_path.push(&safe_name);
-        let mut file_handle = tokio::fs::File::create(file_path).await?;
+        // Only the first `SNIFF_LEN` bytes are buffered for detection; the
+        // remainder is accumulated into `S3_PART_SIZE` parts and streamed up
+        // via a multipart upload instead of holding the whole file in memory.
+        let mut sniff_buffer: Vec<u8> = Vec::with_capacity(crate::validate::SNIFF_LEN);
+        let mut part_buffer: Vec<u8> = Vec::with_capacity(S3_PART_SIZE);
+        let mut validated = false;
+        let mut upload_id: Option<String> = None;
+        let mut parts: Vec<aws_sdk_s3::types::CompletedPart> = Vec::new();
 
-        while let Some(chunk) = field.chunk().await? {
-            file_handle.write_all(&chunk).await?;
+        let upload_result: ApplicationResult<()> = async {
+            while let Some(chunk) = field.chunk().await? {
+                if !validated {
+                    sniff_buffer.extend_from_slice(&chunk);
+                    if sniff_buffer.len() < crate::validate::SNIFF_LEN {
+                        continue;
+                    }
+                    validate_upload(&safe_name, &sniff_buffer)?;
+                    validated = true;
+                    part_buffer.append(&mut sniff_buffer);
+                } else {
+                    part_buffer.extend_from_slice(&chunk);
+                }
+
+                if part_buffer.len() >= S3_PART_SIZE {
+                    let id = self
+                        .ensure_multipart_upload(&key, &mut upload_id)
+                        .await
+                        .map_err(anyhow::Error::from)?;
+                    let full_part =
+                        std::mem::replace(&mut part_buffer, Vec::with_capacity(S3_PART_SIZE));
+                    let completed = self
+                        .upload_part(&key, &id, parts.len(), full_part)
+                        .await
+                        .map_err(anyhow::Error::from)?;
+                    parts.push(completed);
+                }
+            }
+
+            if !validated {
+                validate_upload(&safe_name, &sniff_buffer)?;
+                part_buffer.append(&mut sniff_buffer);
+            }
+
+            Ok(())
         }
-        file_handle.flush().await?;
+        .await;
+
+        if let Err(e) = upload_result {
+            if let Some(id) = &upload_id {
+                let _ = self
+                    .client
+                    .abort_multipart_upload()
+                    .bucket(&self.bucket)
+                    .key(&key)
+                    .upload_id(id)
+                    .send()
+                    .await;
+            }
+            return Err(e);
+        }
+
+        match upload_id {
+            Some(id) => {
+                // The final part may be smaller than `S3_PART_SIZE`, unlike
+                // every part before it.
+                if !part_buffer.is_empty() {
+                    let completed = self
+                        .upload_part(&key, &id, parts.len(), part_buffer)
+                        .await
+                        .map_err(anyhow::Error::from)?;
+                    parts.push(completed);
+                }
+
+                self.client
+                    .complete_multipart_upload()
+                    .bucket(&self.bucket)
+                    .key(&key)
+                    .upload_id(&id)
+                    .multipart_upload(
+                        aws_sdk_s3::types::CompletedMultipartUpload::builder()
+                            .set_parts(Some(parts))
+                            .build(),
+                    )
+                    .send()
+                    .await
+                    .map_err(anyhow::Error::from)?;
+
+                if options.expires_in.is_some() || options.max_downloads.is_some() {
+                    // Multipart uploads can't set metadata on completion, so
+                    // apply it afterwards with a lightweight copy-to-self.
+                    let mut copy_request = self
+                        .client
+                        .copy_object()
+                        .bucket(&self.bucket)
+                        .copy_source(format!("{}/{key}", self.bucket))
+                        .key(&key)
+                        .metadata_directive(aws_sdk_s3::types::MetadataDirective::Replace);
+                    if let Some(expires_in) = options.expires_in {
+                        let expires_at = now_unix().saturating_add(expires_in.as_secs());
+                        copy_request = copy_request.metadata("expires-at", expires_at.to_string());
+                    }
+                    if let Some(max_downloads) = options.max_downloads {
+                        copy_request =
+                            copy_request.metadata("remaining-downloads", max_downloads.to_string());
+                    }
+                    copy_request.send().await.map_err(anyhow::Error::from)?;
+                }
+            }
+            None => {
+                // Upload never reached a full part (a small file) — a single
+                // `put_object` is simpler and cheaper than a multipart upload.
+                let mut request = self
+                    .client
+                    .put_object()
+                    .bucket(&self.bucket)
+                    .key(&key)
+                    .body(part_buffer.into());
+
+                if let Some(expires_in) = options.expires_in {
+                    let expires_at = now_unix().saturating_add(expires_in.as_secs());
+                    request = request.metadata("expires-at", expires_at.to_string());
+                }
+                if let Some(max_downloads) = options.max_downloads {
+                    request = request.metadata("remaining-downloads", max_downloads.to_string());
+                }
+
+                request.send().await.map_err(anyhow::Error::from)?;
+            }
+        }
+
         Ok(Filedata {
             filename: safe_name,
         })
     }
 
-    pub async fn get_all_file_data(&self) -> anyhow::Result<Vec<Filedata>> {
-        let mut file_reader = tokio::fs::read_dir(&self.folder_path).await?;
+    async fn metadata(&self, name: &str) -> ApplicationResult<Option<u64>> {
+        if !is_safe_filename(name) {
+            return Err(ApplicationError {
+                source: anyhow::anyhow!("'{name}' contains path traversal components"),
+                kind: ErrorKind::InvalidFilename,
+            });
+        }
+
+        match self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(self.key_for(name))
+            .send()
+            .await
+        {
+            Ok(output) => Ok(output
+                .content_length()
+                .and_then(|len| u64::try_from(len).ok())),
+            Err(aws_sdk_s3::error::SdkError::ServiceError(e)) if e.err().is_not_found() => {
+                Ok(None)
+            }
+            Err(e) => Err(anyhow::Error::from(e).into()),
+        }
+    }
+
+    async fn open(&self, name: &str, range: Option<ByteRange>) -> ApplicationResult<OpenOutcome> {
+        if !is_safe_filename(name) {
+            return Err(ApplicationError {
+                source: anyhow::anyhow!("'{name}' contains path traversal components"),
+                kind: ErrorKind::InvalidFilename,
+            });
+        }
+
+        let mut request = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(self.key_for(name));
+
+        if let Some(r) = range {
+            request = request.range(format!("bytes={}-{}", r.start, r.end));
+        }
+
+        let output = match request.send().await {
+            Ok(output) => output,
+            Err(aws_sdk_s3::error::SdkError::ServiceError(e)) if e.err().is_no_such_key() => {
+                return Ok(OpenOutcome::NotFound);
+            }
+            Err(e) => return Err(anyhow::Error::from(e).into()),
+        };
+
+        let expires_at: Option<u64> = output
+            .metadata()
+            .and_then(|m| m.get("expires-at"))
+            .and_then(|v| v.parse().ok());
+        let remaining_downloads: Option<u32> = output
+            .metadata()
+            .and_then(|m| m.get("remaining-downloads"))
+            .and_then(|v| v.parse().ok());
+
+        let record = UploadRecord {
+            expires_at,
+            remaining_downloads,
+        };
+        if !record.is_live() {
+            self.delete(name).await.map_err(anyhow::Error::from)?;
+            return Ok(OpenOutcome::Gone);
+        }
+
+        // Only a full (non-range) request consumes a download, matching
+        // FileStore::open — a `Range` request is part of a larger transfer
+        // and shouldn't burn the limit on its own.
+        //
+        // S3 has no atomic decrement, so this is best-effort: the update is
+        // conditioned on the object's ETag still matching what we just read,
+        // but if another concurrent download already bumped the count, our
+        // copy is rejected and we still serve this download rather than fail
+        // it outright — under heavy concurrency `max_downloads` on the S3
+        // backend is a soft limit, not a hard guarantee.
+        if range.is_none() {
+            if let Some(remaining) = remaining_downloads {
+                let remaining = remaining.saturating_sub(1);
+                let mut copy_request = self
+                    .client
+                    .copy_object()
+                    .bucket(&self.bucket)
+                    .copy_source(format!("{}/{}", self.bucket, self.key_for(name)))
+                    .key(self.key_for(name))
+                    .metadata("remaining-downloads", remaining.to_string())
+                    .metadata_directive(aws_sdk_s3::types::MetadataDirective::Replace);
+                if let Some(expires_at) = expires_at {
+                    copy_request = copy_request.metadata("expires-at", expires_at.to_string());
+                }
+                if let Some(etag) = output.e_tag() {
+                    copy_request = copy_request.copy_source_if_match(etag);
+                }
+                if let Err(e) = copy_request.send().await {
+                    tracing::warn!(
+                        "best-effort remaining-downloads update for '{name}' lost a race: {e}"
+                    );
+                }
+            }
+        }
+
+        Ok(OpenOutcome::Found(Box::pin(output.body.into_async_read())))
+    }
+
+    async fn list(&self) -> ApplicationResult<Vec<Filedata>> {
         let mut files = vec![];
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(&self.prefix)
+            .into_paginator()
+            .send();
 
-        while let Some(file) = file_reader.next_entry().await? {
-            if file.file_type().await?.is_dir() {
-                continue;
+        while let Some(page) = pages.next().await {
+            let page = page.map_err(anyhow::Error::from)?;
+            for object in page.contents() {
+                let Some(key) = object.key() else {
+                    continue;
+                };
+                let filename = key
+                    .strip_prefix(&format!("{}/", self.prefix))
+                    .unwrap_or(key)
+                    .to_string();
+                files.push(Filedata { filename });
             }
-            files.push(Filedata::from(file));
         }
 
         Ok(files)
     }
 
-    pub async fn download_file(&self, query: &FileDownloadQuery) -> anyhow::Result<Option<File>> {
-        let path = std::path::Path::new(&query.filename);
-        let has_traversal = path.components().any(|c| {
-            matches!(
-                c,
-                std::path::Component::ParentDir
-                    | std::path::Component::RootDir
-                    | std::path::Component::Prefix(_)
+    async fn info(&self) -> String {
+        format!(
+            "Uploaded {} files to bucket '{}'",
+            self.upload_count.lock().await,
+            self.bucket
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            )
-        });
+    #[test]
+    fn a_record_with_no_expiry_or_limit_is_always_live() {
+        let record = UploadRecord {
+            expires_at: None,
+            remaining_downloads: None,
+        };
+        assert!(record.is_live());
+    }
 
-        let is_invalid_filename = has_traversal || query.filename.is_empty();
-        if is_invalid_filename {
-            return Ok(None);
-        }
-        let mut file_path = self.folder_path.clone();
-        file_path.push(&query.filename);
-        let file = match tokio::fs::File::open(file_path).await {
-            Ok(f) => f,
-            Err(_) => return Ok(None),
+    #[test]
+    fn an_unexpired_ttl_is_live() {
+        let record = UploadRecord {
+            expires_at: Some(now_unix().saturating_add(60)),
+            remaining_downloads: None,
         };
+        assert!(record.is_live());
+    }
 
-        Ok(Some(file))
+    #[test]
+    fn a_ttl_in_the_past_is_not_live() {
+        let record = UploadRecord {
+            expires_at: Some(now_unix().saturating_sub(1)),
+            remaining_downloads: None,
+        };
+        assert!(!record.is_live());
+    }
+
+    #[test]
+    fn remaining_downloads_above_zero_is_live() {
+        let record = UploadRecord {
+            expires_at: None,
+            remaining_downloads: Some(1),
+        };
+        assert!(record.is_live());
+    }
+
+    #[test]
+    fn zero_remaining_downloads_is_not_live() {
+        let record = UploadRecord {
+            expires_at: None,
+            remaining_downloads: Some(0),
+        };
+        assert!(!record.is_live());
     }
 }
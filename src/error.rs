@@ -1,6 +1,6 @@
 use axum::{http::StatusCode, response::IntoResponse};
-use log::warn;
 use thiserror::Error;
+use tracing::warn;
 
 pub type ApplicationResult<T> = Result<T, ApplicationError>;
 
@@ -21,6 +21,8 @@ impl std::fmt::Display for ApplicationError {
 pub enum ErrorKind {
     #[strum(to_string = "File '{0}' not found on the server!")]
     FileNotFound(String),
+    #[strum(to_string = "File '{0}' has expired or is out of downloads!")]
+    Gone(String),
     #[strum(to_string = "Invalid filename!")]
     InvalidFilename,
     #[strum(to_string = "File upload failed!")]
@@ -48,6 +50,7 @@ impl IntoResponse for ApplicationError {
     fn into_response(self) -> axum::response::Response {
         let status_code = match self.kind {
             ErrorKind::FileNotFound(_) => StatusCode::NOT_FOUND,
+            ErrorKind::Gone(_) => StatusCode::GONE,
             ErrorKind::Internal => StatusCode::INTERNAL_SERVER_ERROR,
             ErrorKind::FileUpload | ErrorKind::InvalidFilename => StatusCode::BAD_REQUEST,
         };
@@ -87,3 +90,16 @@ impl From<std::io::Error> for ApplicationError {
         }
     }
 }
+
+/// Fallback for error sources without a dedicated `ErrorKind` (e.g. index
+/// serialization or object-storage SDK failures); always mapped to
+/// `ErrorKind::Internal`. Use a specific `From` impl or construct
+/// `ApplicationError` directly when a more precise kind is known.
+impl From<anyhow::Error> for ApplicationError {
+    fn from(value: anyhow::Error) -> Self {
+        Self {
+            source: value,
+            kind: ErrorKind::Internal,
+        }
+    }
+}
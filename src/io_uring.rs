@@ -0,0 +1,195 @@
+//! Optional io_uring-backed file I/O, enabled via the `io-uring` Cargo
+//! feature and the `--io-uring` flag. `tokio-uring` needs its own
+//! single-threaded runtime, so every call here is bounced through
+//! [`tokio::task::spawn_blocking`] and driven by `tokio_uring::start`
+//! rather than awaited directly on the main Tokio runtime. Both functions
+//! fall back to a `tokio::fs`-based implementation when the feature is
+//! compiled out or the target isn't Linux, so callers can check
+//! [`is_available`] once and call them unconditionally afterwards.
+//!
+//! Both directions are streamed through a bounded channel rather than
+//! buffering a whole transfer in memory, so large concurrent
+//! uploads/downloads don't balloon RSS. Writes pass each chunk through as
+//! the caller sends it; reads are produced in [`STREAM_CHUNK_SIZE`] pieces.
+
+use std::{
+    path::PathBuf,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tokio::{
+    io::{AsyncRead, ReadBuf},
+    sync::mpsc,
+};
+
+/// Chunk size used by [`read_range`] when streaming through the ring (or the
+/// `tokio::fs` fallback), bounding how much of a single transfer is ever held in memory
+/// at once.
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Whether the io_uring path is compiled in and the kernel actually supports
+/// it; the `--io-uring` flag is checked by the caller. Probing means briefly spinning up a ring, since older kernels (or
+/// sandboxes with restrictive seccomp policies) can reject the setup call.
+pub fn is_available() -> bool {
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    {
+        tokio_uring::Runtime::new(&tokio_uring::builder()).is_ok()
+    }
+
+    #[cfg(not(all(feature = "io-uring", target_os = "linux")))]
+    {
+        false
+    }
+}
+
+/// Writes chunks received over `rx` to `path` in order, as they arrive,
+/// rather than collecting the whole upload in memory first.
+pub async fn write_stream(path: PathBuf, mut rx: mpsc::Receiver<Vec<u8>>) -> std::io::Result<()> {
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    {
+        return tokio::task::spawn_blocking(move || {
+            tokio_uring::start(async move {
+                let file = tokio_uring::fs::File::create(&path).await?;
+                let mut offset: u64 = 0;
+                while let Some(chunk) = rx.recv().await {
+                    let chunk_len = u64::try_from(chunk.len()).unwrap_or(u64::MAX);
+                    let (res, _buf) = file.write_at(chunk, offset).await;
+                    res?;
+                    offset = offset.saturating_add(chunk_len);
+                }
+                file.sync_all().await?;
+                file.close().await
+            })
+        })
+        .await
+        .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+    }
+
+    #[cfg(not(all(feature = "io-uring", target_os = "linux")))]
+    {
+        use tokio::io::AsyncWriteExt;
+
+        let mut file = tokio::fs::File::create(&path).await?;
+        while let Some(chunk) = rx.recv().await {
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await
+    }
+}
+
+/// Bridges a channel of incrementally-produced chunks into an [`AsyncRead`],
+/// so [`read_range`] can hand callers a stream instead of holding the whole
+/// requested range in memory.
+struct ChunkReader {
+    rx: mpsc::Receiver<std::io::Result<Vec<u8>>>,
+    pending: Vec<u8>,
+    pending_pos: usize,
+}
+
+impl AsyncRead for ChunkReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.pending_pos < this.pending.len() {
+                let available = &this.pending[this.pending_pos..];
+                let n = available.len().min(buf.remaining());
+                buf.put_slice(&available[..n]);
+                this.pending_pos = this.pending_pos.saturating_add(n);
+                return Poll::Ready(Ok(()));
+            }
+
+            match this.rx.poll_recv(cx) {
+                Poll::Ready(Some(Ok(chunk))) => {
+                    this.pending = chunk;
+                    this.pending_pos = 0;
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(e)),
+                Poll::Ready(None) => return Poll::Ready(Ok(())),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Reads `len` bytes starting at `start` from `path`, streaming them to the
+/// caller in [`STREAM_CHUNK_SIZE`] pieces instead of buffering the whole
+/// range in memory.
+pub fn read_range(path: PathBuf, start: u64, len: u64) -> Pin<Box<dyn AsyncRead + Send>> {
+    let (tx, rx) = mpsc::channel::<std::io::Result<Vec<u8>>>(4);
+
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    tokio::spawn(async move {
+        let result = tokio::task::spawn_blocking(move || {
+            tokio_uring::start(async move {
+                let file = tokio_uring::fs::File::open(&path).await?;
+                let mut offset = start;
+                let mut remaining = len;
+                while remaining > 0 {
+                    let want =
+                        usize::try_from(remaining.min(STREAM_CHUNK_SIZE as u64)).unwrap_or(0);
+                    let buf = Vec::with_capacity(want);
+                    let (res, mut buf) = file.read_at(buf, offset).await;
+                    let read = res?;
+                    if read == 0 {
+                        break;
+                    }
+                    buf.truncate(read);
+                    let read_u64 = u64::try_from(read).unwrap_or(0);
+                    offset = offset.saturating_add(read_u64);
+                    remaining = remaining.saturating_sub(read_u64.min(remaining));
+                    if tx.send(Ok(buf)).await.is_err() {
+                        break;
+                    }
+                }
+                file.close().await
+            })
+        })
+        .await
+        .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+
+        if let Err(e) = result {
+            let _ = tx.send(Err(e)).await;
+        }
+    });
+
+    #[cfg(not(all(feature = "io-uring", target_os = "linux")))]
+    tokio::spawn(async move {
+        use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+        let result: std::io::Result<()> = async {
+            let mut file = tokio::fs::File::open(&path).await?;
+            file.seek(std::io::SeekFrom::Start(start)).await?;
+            let mut remaining = len;
+            let mut buf = vec![0u8; STREAM_CHUNK_SIZE];
+            while remaining > 0 {
+                let want = usize::try_from(remaining.min(STREAM_CHUNK_SIZE as u64)).unwrap_or(0);
+                let read = file.read(&mut buf[..want]).await?;
+                if read == 0 {
+                    break;
+                }
+                let read_u64 = u64::try_from(read).unwrap_or(0);
+                remaining = remaining.saturating_sub(read_u64.min(remaining));
+                if tx.send(Ok(buf[..read].to_vec())).await.is_err() {
+                    break;
+                }
+            }
+            Ok(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            let _ = tx.send(Err(e)).await;
+        }
+    });
+
+    Box::pin(ChunkReader {
+        rx,
+        pending: Vec::new(),
+        pending_pos: 0,
+    })
+}
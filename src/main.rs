@@ -32,18 +32,18 @@
 #![deny(clippy::transmute_ptr_to_ref)] // Prevent unsafe transmutation from pointers to references
 #![deny(clippy::transmute_undefined_repr)] // Detect transmutes with potentially undefined representations
 
-use axum::{
-    Router,
-    body::Bytes,
-    extract::{Multipart, State, multipart::Field},
-    http::StatusCode,
-    response::IntoResponse,
-    routing::{get, post},
-};
-use clap::Parser;
-use fs2::FileExt;
+mod controllers;
+mod error;
+mod io_uring;
+mod middleware;
+mod routes;
+mod validate;
+
+use axum::{Router, routing::get};
+use clap::{Parser, ValueEnum};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+
+use controllers::{FileStore, ObjectStore, Store};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -52,97 +52,71 @@ struct Environment {
     #[arg(short, long, default_value_t=String::from("localhost:3000"))]
     pub server_address: String,
 
-    /// Folder where uploads are stored at
+    /// Folder where uploads are stored at, when `--backend local` is used
     #[arg(short, long, default_value_t=String::from("./uploads"))]
     pub folder: String,
 
     #[arg(short, long, default_value_t = false)]
     pub verbose: bool,
-}
 
-pub struct FileUpload {
-    pub bytes: Bytes,
-    pub filename: String,
+    /// Where uploaded files are stored
+    #[arg(long, value_enum, default_value_t = StorageBackend::Local)]
+    pub backend: StorageBackend,
+
+    /// S3-compatible endpoint URL, required when `--backend s3` is used
+    #[arg(long)]
+    pub s3_endpoint: Option<String>,
+
+    /// Bucket name to store uploads in, required when `--backend s3` is used
+    #[arg(long)]
+    pub bucket: Option<String>,
+
+    /// Key prefix applied to every object stored in the bucket
+    #[arg(long, default_value_t=String::from(""))]
+    pub prefix: String,
+
+    /// Only accept uploads whose sniffed content type is in this list (e.g.
+    /// `image/png,image/jpeg`). Empty means no restriction.
+    #[arg(long, value_delimiter = ',')]
+    pub allowed_types: Vec<String>,
+
+    /// Reject uploads whose sniffed content type is in this list, even if it
+    /// is also present in `--allowed-types`.
+    #[arg(long, value_delimiter = ',')]
+    pub denied_types: Vec<String>,
+
+    /// Use io_uring for local file I/O instead of `tokio::fs`. Requires the
+    /// `io-uring` Cargo feature and a Linux kernel with io_uring support;
+    /// otherwise logs a warning at startup and falls back to `tokio::fs`.
+    #[arg(long, default_value_t = false)]
+    pub io_uring: bool,
 }
 
-impl FileUpload {
-    async fn new(value: Field<'_>) -> Option<Self> {
-        let filename = value.file_name()?.to_string();
-        let bytes = value.bytes().await.ok()?;
-
-        Some(Self { filename, bytes })
-    }
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum StorageBackend {
+    Local,
+    S3,
 }
 
-#[derive(Debug)]
-pub struct FileUploader {
-    #[allow(dead_code)]
-    lock_file: std::fs::File,
-    folder_path: std::path::PathBuf,
-    upload_count: u64,
+lazy_static::lazy_static! {
+    static ref ENVIRONMENT: Environment = Environment::parse();
 }
 
-impl FileUploader {
-    fn new(folder_path: std::path::PathBuf) -> anyhow::Result<Self> {
-        std::fs::create_dir_all(&folder_path)?;
-
-        let mut lockfile_path = folder_path.clone();
-        lockfile_path.push(".lock");
-        let lock_file = std::fs::File::options()
-            .read(true)
-            .write(true)
-            .truncate(true)
-            .create(true)
-            .open(lockfile_path)?;
-        lock_file.try_lock_exclusive()?;
-
-        Ok(Self {
-            lock_file,
-            folder_path,
-            upload_count: 0,
-        })
-    }
-
-    pub fn init(folder_path: &str) -> anyhow::Result<Self> {
-        let as_path = std::path::PathBuf::from(folder_path);
-        Self::new(as_path)
-    }
-
-    pub fn print_info(&self) {
-        println!("{}", self.get_info());
-    }
+pub type AppState = Arc<dyn Store>;
 
-    pub fn get_info(&self) -> String {
-        format!(
-            "Uploaded {} files to '{}'",
-            self.upload_count,
-            self.folder_path.display()
-        )
-    }
+fn init_tracing() {
+    use tracing_subscriber::prelude::*;
 
-    pub async fn upload_file(&mut self, file: FileUpload) -> anyhow::Result<()> {
-        let mut file_path = self.folder_path.clone();
-        file_path.push(file.filename);
-        tokio::fs::write(file_path, file.bytes).await?;
-
-        self.upload_count = self.upload_count.checked_add(1).ok_or_else(|| {
-            anyhow::anyhow!(
-                "Cannot upload more files: counter at maximum value of {}",
-                self.upload_count
-            )
-        })?;
-        Ok(())
-    }
-}
-
-lazy_static::lazy_static! {
-    static ref ENVIRONMENT: Environment = Environment::parse();
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::EnvFilter::from_default_env())
+        .with(tracing_subscriber::fmt::layer())
+        .init();
 }
 
-pub type AppState = Arc<Mutex<FileUploader>>;
-
 #[tokio::main]
 async fn main() {
+    init_tracing();
+
     match run().await {
         Ok(_) => (),
         Err(e) => {
@@ -158,12 +132,44 @@ async fn main() {
     }
 }
 
+async fn build_store() -> anyhow::Result<AppState> {
+    match ENVIRONMENT.backend {
+        StorageBackend::Local => {
+            let store = Arc::new(FileStore::init(&ENVIRONMENT.folder, ENVIRONMENT.io_uring)?);
+            store.clone().spawn_reaper();
+            Ok(store)
+        }
+        StorageBackend::S3 => {
+            let bucket = ENVIRONMENT
+                .bucket
+                .clone()
+                .ok_or_else(|| anyhow::anyhow!("--bucket is required when --backend is s3"))?;
+            let endpoint = ENVIRONMENT
+                .s3_endpoint
+                .clone()
+                .ok_or_else(|| anyhow::anyhow!("--s3-endpoint is required when --backend is s3"))?;
+
+            let store = Arc::new(
+                ObjectStore::init(endpoint, bucket, ENVIRONMENT.prefix.clone()).await?,
+            );
+            store.clone().spawn_reaper();
+            Ok(store)
+        }
+    }
+}
+
 async fn run() -> anyhow::Result<()> {
-    let shared_state: AppState = Arc::new(Mutex::new(FileUploader::init(&ENVIRONMENT.folder)?));
+    let shared_state: AppState = build_store().await?;
     let app = Router::new()
-        .route("/version", get(version_route))
-        .route("/info", get(info_route))
-        .route("/upload", post(upload_route))
+        .route("/version", get(routes::get_version))
+        .route("/info", get(routes::get_info))
+        .route(
+            "/upload",
+            get(routes::get_upload_file_page).post(routes::post_upload),
+        )
+        .route("/files", get(routes::get_file_display_page))
+        .route("/download", get(routes::get_download_file))
+        .route_layer(axum::middleware::from_fn(middleware::logging_middleware))
         .with_state(shared_state);
 
     let listener = tokio::net::TcpListener::bind(&ENVIRONMENT.server_address).await?;
@@ -171,38 +177,3 @@ async fn run() -> anyhow::Result<()> {
     axum::serve(listener, app).await?;
     Ok(())
 }
-
-async fn info_route(State(state): State<AppState>) -> String {
-    state.lock().await.get_info()
-}
-
-async fn upload_route(
-    State(state): State<AppState>,
-    mut multipart: Multipart,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    while let Some(field) = multipart.next_field().await.map_err(|e| {
-        (
-            StatusCode::BAD_REQUEST,
-            format!("Multipart read error: {e}"),
-        )
-    })? {
-        let file = FileUpload::new(field).await.ok_or((
-            StatusCode::BAD_REQUEST,
-            "Invalid file metadata or contents".to_string(),
-        ))?;
-
-        let mut uploader = state.lock().await;
-        uploader.upload_file(file).await.map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("File save failed: {e}"),
-            )
-        })?;
-    }
-
-    Ok((StatusCode::OK, "File uploaded successfully!"))
-}
-
-async fn version_route() -> &'static str {
-    env!("CARGO_PKG_VERSION")
-}
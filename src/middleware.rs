@@ -1,24 +1,42 @@
-use axum::{body::Body, http::Request, middleware::Next, response::Response};
-use log::info;
-use std::time::Instant;
+use axum::{body::Body, extract::MatchedPath, http::Request, middleware::Next, response::Response};
+use tracing::Instrument;
 
+/// Wraps every request in a span carrying the method, URI, a generated
+/// request-id, and the matched route, then emits the final status and
+/// duration as a structured event once the response is ready. Anything
+/// logged deeper in the call stack (including [`crate::error::ApplicationError::into_response`])
+/// runs inside this span, so it's automatically correlated by request-id.
 pub async fn logging_middleware(req: Request<Body>, next: Next) -> Response {
-    let method = req.method().to_string();
-    let uri = req.uri().to_string();
+    let method = req.method().clone();
+    let uri = req.uri().clone();
+    let matched_path = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_default();
+    let request_id = uuid::Uuid::new_v4();
 
-    let start = Instant::now();
-    let response = next.run(req).await;
+    let span = tracing::info_span!(
+        "request",
+        %method,
+        %uri,
+        %request_id,
+        route = %matched_path,
+    );
 
-    let status = response.status();
-    let duration = start.elapsed();
+    async move {
+        let start = std::time::Instant::now();
+        let response = next.run(req).await;
+        let duration = start.elapsed();
 
-    info!(
-        "{} {} {} ({} ms)",
-        method,
-        uri,
-        status,
-        duration.as_millis()
-    );
+        tracing::info!(
+            status = %response.status(),
+            duration_ms = duration.as_millis(),
+            "request completed"
+        );
 
-    response
+        response
+    }
+    .instrument(span)
+    .await
 }
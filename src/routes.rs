@@ -2,31 +2,74 @@ use askama::Template;
 use axum::{
     body::Body,
     extract::{Multipart, Query, State},
-    http::{StatusCode, header},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
     response::{Html, IntoResponse},
 };
-use log::{info, warn};
 use tokio_util::io::ReaderStream;
+use tracing::{info, instrument, warn};
 
-use crate::{AppState, controllers::Filedata};
+use crate::{
+    AppState,
+    controllers::{ByteRange, Filedata, OpenOutcome, UploadOptions},
+    error::{ApplicationError, ApplicationResult, ErrorKind},
+};
+
+/// Why [`parse_range_header`] didn't produce a usable range. Per RFC 7233, a
+/// `Malformed` header must be ignored (serve the full 200 response), while a
+/// well-formed but `Unsatisfiable` range is rejected with 416.
+#[derive(Debug, PartialEq, Eq)]
+enum RangeParseError {
+    Malformed,
+    Unsatisfiable,
+}
+
+/// Parses an RFC 7233 `Range: bytes=start-end` header against a known total
+/// length, resolving open-ended (`bytes=500-`) and suffix (`bytes=-500`)
+/// forms. Multi-range requests (`bytes=0-1,2-3`) aren't supported and are
+/// treated as malformed, same as any other unparseable header.
+fn parse_range_header(raw: &str, total_len: u64) -> Result<ByteRange, RangeParseError> {
+    let spec = raw.strip_prefix("bytes=").ok_or(RangeParseError::Malformed)?;
+    if spec.contains(',') {
+        return Err(RangeParseError::Malformed);
+    }
+    let (start_str, end_str) = spec.split_once('-').ok_or(RangeParseError::Malformed)?;
+
+    let range = if start_str.is_empty() {
+        let suffix_len: u64 = end_str.parse().map_err(|_| RangeParseError::Malformed)?;
+        ByteRange {
+            start: total_len.saturating_sub(suffix_len),
+            end: total_len.saturating_sub(1),
+        }
+    } else {
+        let start: u64 = start_str.parse().map_err(|_| RangeParseError::Malformed)?;
+        let end = if end_str.is_empty() {
+            total_len.saturating_sub(1)
+        } else {
+            end_str.parse().map_err(|_| RangeParseError::Malformed)?
+        };
+        ByteRange {
+            start,
+            end: end.min(total_len.saturating_sub(1)),
+        }
+    };
+
+    if range.start >= total_len || range.start > range.end {
+        return Err(RangeParseError::Unsatisfiable);
+    }
+
+    Ok(range)
+}
 
 pub async fn get_info(State(state): State<AppState>) -> String {
-    state.get_info().await
+    state.info().await
 }
 
 #[derive(Template)]
 #[template(path = "upload.html")]
 struct UploadTemplate;
 
-pub async fn get_upload_file_page() -> Result<impl IntoResponse, (StatusCode, String)> {
-    let template = UploadTemplate.render().map_err(|e| {
-        warn!("Template render error: {e}");
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Template render error: {e}"),
-        )
-    })?;
-
+pub async fn get_upload_file_page() -> ApplicationResult<impl IntoResponse> {
+    let template = UploadTemplate.render()?;
     Ok(Html(template))
 }
 
@@ -38,24 +81,9 @@ struct FileDisplayTemplate<'a> {
 
 pub async fn get_file_display_page(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let files = state.get_all_file_data().await.map_err(|e| {
-        warn!("Directory read error: {e}");
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Directory read error: {e}"),
-        )
-    })?;
-    let template = FileDisplayTemplate { files: &files }
-        .render()
-        .map_err(|e| {
-            warn!("Template render error: {e}");
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Template render error: {e}"),
-            )
-        })?;
-
+) -> ApplicationResult<impl IntoResponse> {
+    let files = state.list().await?;
+    let template = FileDisplayTemplate { files: &files }.render()?;
     Ok(Html(template))
 }
 
@@ -64,61 +92,136 @@ pub struct FileDownloadQuery {
     pub filename: String,
 }
 
+#[instrument(skip(state))]
 pub async fn get_download_file(
     State(state): State<AppState>,
     Query(query): Query<FileDownloadQuery>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let file = match state.download_file(&query).await {
-        Ok(None) => {
-            warn!("File '{}' not found", query.filename);
-            return Err((
-                StatusCode::NOT_FOUND,
-                format!("File '{}' not found", query.filename),
-            ));
+    request_headers: HeaderMap,
+) -> ApplicationResult<impl IntoResponse> {
+    let total_len = match state.metadata(&query.filename).await? {
+        Some(len) => len,
+        None => {
+            return Err(ApplicationError {
+                source: anyhow::anyhow!("file '{}' not found", query.filename),
+                kind: ErrorKind::FileNotFound(query.filename.clone()),
+            });
+        }
+    };
+
+    let range = match request_headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+    {
+        Some(raw) => match parse_range_header(raw, total_len) {
+            Ok(range) => Some(range),
+            Err(RangeParseError::Unsatisfiable) => {
+                warn!("Unsatisfiable range '{}' for file '{}'", raw, query.filename);
+                let mut response_headers = HeaderMap::new();
+                response_headers.insert(
+                    header::CONTENT_RANGE,
+                    HeaderValue::from_str(&format!("bytes */{total_len}"))
+                        .unwrap_or(HeaderValue::from_static("bytes */0")),
+                );
+                return Ok((
+                    StatusCode::RANGE_NOT_SATISFIABLE,
+                    response_headers,
+                    Body::empty(),
+                )
+                    .into_response());
+            }
+            Err(RangeParseError::Malformed) => {
+                warn!(
+                    "Malformed range '{}' for file '{}', ignoring and serving the full file",
+                    raw, query.filename
+                );
+                None
+            }
+        },
+        None => None,
+    };
+
+    let file = match state.open(&query.filename, range).await? {
+        OpenOutcome::NotFound => {
+            return Err(ApplicationError {
+                source: anyhow::anyhow!("file '{}' not found", query.filename),
+                kind: ErrorKind::FileNotFound(query.filename.clone()),
+            });
         }
-        Ok(Some(s)) => s,
-        Err(e) => {
-            warn!("Failed to download file ({}): {}", query.filename, e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to download file ({}): {}", query.filename, e),
-            ));
+        OpenOutcome::Gone => {
+            return Err(ApplicationError {
+                source: anyhow::anyhow!(
+                    "file '{}' has expired or is out of downloads",
+                    query.filename
+                ),
+                kind: ErrorKind::Gone(query.filename.clone()),
+            });
         }
+        OpenOutcome::Found(s) => s,
     };
 
     let stream = ReaderStream::new(file);
     let body = Body::from_stream(stream);
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    response_headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/octet-stream"),
+    );
+    response_headers.insert(
+        header::CONTENT_DISPOSITION,
+        HeaderValue::from_str(&format!("attachment; filename=\"{}\"", query.filename))
+            .unwrap_or(HeaderValue::from_static("attachment")),
+    );
+
+    let status = if let Some(r) = range {
+        response_headers.insert(
+            header::CONTENT_RANGE,
+            HeaderValue::from_str(&format!("bytes {}-{}/{total_len}", r.start, r.end))
+                .unwrap_or(HeaderValue::from_static("")),
+        );
+        response_headers.insert(
+            header::CONTENT_LENGTH,
+            HeaderValue::from_str(&(r.end.saturating_sub(r.start).saturating_add(1)).to_string())
+                .unwrap_or(HeaderValue::from_static("0")),
+        );
+        StatusCode::PARTIAL_CONTENT
+    } else {
+        response_headers.insert(
+            header::CONTENT_LENGTH,
+            HeaderValue::from_str(&total_len.to_string()).unwrap_or(HeaderValue::from_static("0")),
+        );
+        StatusCode::OK
+    };
+
     info!("File '{}' found, starting download!", query.filename);
-    Ok((
-        [
-            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
-            (
-                header::CONTENT_DISPOSITION,
-                format!("attachment; filename=\"{}\"", query.filename),
-            ),
-        ],
-        body,
-    ))
+    Ok((status, response_headers, body).into_response())
+}
+
+/// Per-upload expiry settings, taken as query parameters rather than
+/// multipart fields: a `multipart::Multipart` stream can only be read
+/// forward once, so fields would have to arrive before every file field to
+/// apply to it. Query parameters are known in full before the body is even
+/// read, so every file in the upload gets the same options unambiguously.
+#[derive(serde::Deserialize, Debug, Default)]
+pub struct UploadOptionsQuery {
+    pub expires_in_secs: Option<u64>,
+    pub max_downloads: Option<u32>,
 }
 
+#[instrument(skip(state, multipart))]
 pub async fn post_upload(
     State(state): State<AppState>,
+    Query(query): Query<UploadOptionsQuery>,
     mut multipart: Multipart,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    while let Some(field) = multipart.next_field().await.map_err(|e| {
-        warn!("Multipart read error: {e}");
-        (
-            StatusCode::BAD_REQUEST,
-            format!("Multipart read error: {e}"),
-        )
-    })? {
-        let file_data = state.upload_file(field).await.map_err(|e| {
-            warn!("File save failed: {e}");
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("File save failed: {e}"),
-            )
-        })?;
+) -> ApplicationResult<impl IntoResponse> {
+    let options = UploadOptions {
+        expires_in: query.expires_in_secs.map(std::time::Duration::from_secs),
+        max_downloads: query.max_downloads,
+    };
+
+    while let Some(field) = multipart.next_field().await? {
+        let file_data = state.save(field, options).await?;
         info!("File '{}' saved successfully!", &file_data.filename);
     }
 
@@ -128,3 +231,83 @@ pub async fn post_upload(
 pub async fn get_version() -> &'static str {
     env!("CARGO_PKG_VERSION")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_plain_range() {
+        let range = parse_range_header("bytes=0-499", 1000).unwrap();
+        assert_eq!(range.start, 0);
+        assert_eq!(range.end, 499);
+    }
+
+    #[test]
+    fn resolves_an_open_ended_range() {
+        let range = parse_range_header("bytes=500-", 1000).unwrap();
+        assert_eq!(range.start, 500);
+        assert_eq!(range.end, 999);
+    }
+
+    #[test]
+    fn resolves_a_suffix_range() {
+        let range = parse_range_header("bytes=-500", 1000).unwrap();
+        assert_eq!(range.start, 500);
+        assert_eq!(range.end, 999);
+    }
+
+    #[test]
+    fn clamps_an_end_past_the_total_length() {
+        let range = parse_range_header("bytes=0-9999", 1000).unwrap();
+        assert_eq!(range.start, 0);
+        assert_eq!(range.end, 999);
+    }
+
+    #[test]
+    fn a_suffix_longer_than_the_file_clamps_to_the_start() {
+        let range = parse_range_header("bytes=-9999", 1000).unwrap();
+        assert_eq!(range.start, 0);
+        assert_eq!(range.end, 999);
+    }
+
+    #[test]
+    fn rejects_a_start_past_the_total_length_as_unsatisfiable() {
+        assert_eq!(
+            parse_range_header("bytes=1000-1999", 1000),
+            Err(RangeParseError::Unsatisfiable)
+        );
+    }
+
+    #[test]
+    fn rejects_a_start_after_the_end_as_unsatisfiable() {
+        assert_eq!(
+            parse_range_header("bytes=500-100", 1000),
+            Err(RangeParseError::Unsatisfiable)
+        );
+    }
+
+    #[test]
+    fn rejects_a_missing_bytes_prefix_as_malformed() {
+        assert_eq!(
+            parse_range_header("items=0-499", 1000),
+            Err(RangeParseError::Malformed)
+        );
+    }
+
+    #[test]
+    fn rejects_non_numeric_bounds_as_malformed() {
+        assert_eq!(
+            parse_range_header("bytes=abc-def", 1000),
+            Err(RangeParseError::Malformed)
+        );
+    }
+
+    #[test]
+    fn rejects_a_multi_range_request_as_malformed() {
+        assert_eq!(
+            parse_range_header("bytes=0-1,2-3", 1000),
+            Err(RangeParseError::Malformed)
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Magic-byte sniffing so uploads can be accepted or rejected by their real
+//! content instead of trusting the declared filename.
+
+/// Number of leading bytes buffered before sniffing; large enough to cover
+/// every signature below.
+pub const SNIFF_LEN: usize = 8;
+
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (&[0x89, 0x50, 0x4E, 0x47], "image/png"),
+    (&[0xFF, 0xD8, 0xFF], "image/jpeg"),
+    (&[0x25, 0x50, 0x44, 0x46], "application/pdf"),
+    (&[0x50, 0x4B, 0x03, 0x04], "application/zip"),
+];
+
+/// Identifies the format of `bytes` from its leading magic number, if known.
+pub fn sniff(bytes: &[u8]) -> Option<&'static str> {
+    SIGNATURES
+        .iter()
+        .find(|(signature, _)| bytes.starts_with(signature))
+        .map(|(_, mime)| *mime)
+}
+
+/// Whether `sniffed` is acceptable given the configured allow/deny lists. An
+/// empty allow list means "no restriction"; a non-empty one acts as a strict
+/// allowlist. The deny list always wins.
+pub fn is_allowed(sniffed: Option<&str>, allowed: &[String], denied: &[String]) -> bool {
+    let Some(mime) = sniffed else {
+        return allowed.is_empty();
+    };
+
+    if denied.iter().any(|d| d == mime) {
+        return false;
+    }
+
+    allowed.is_empty() || allowed.iter().any(|a| a == mime)
+}
+
+/// Whether `filename`'s extension agrees with the sniffed `mime`. A filename
+/// without an extension never disagrees, since there's nothing declared to
+/// contradict.
+pub fn extension_matches(filename: &str, mime: &str) -> bool {
+    let extension = std::path::Path::new(filename)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase);
+
+    match (extension.as_deref(), mime) {
+        (None, _) => true,
+        (Some("png"), "image/png") => true,
+        (Some("jpg" | "jpeg"), "image/jpeg") => true,
+        (Some("pdf"), "application/pdf") => true,
+        (Some("zip"), "application/zip") => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniffs_a_known_signature() {
+        assert_eq!(sniff(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A]), Some("image/png"));
+        assert_eq!(sniff(&[0xFF, 0xD8, 0xFF, 0xE0]), Some("image/jpeg"));
+    }
+
+    #[test]
+    fn sniff_returns_none_for_unknown_bytes() {
+        assert_eq!(sniff(b"plain text"), None);
+    }
+
+    #[test]
+    fn sniff_handles_input_shorter_than_any_signature() {
+        assert_eq!(sniff(&[0x25]), None);
+    }
+
+    #[test]
+    fn empty_allow_list_permits_anything() {
+        assert!(is_allowed(Some("image/png"), &[], &[]));
+        assert!(is_allowed(None, &[], &[]));
+    }
+
+    #[test]
+    fn unknown_sniff_is_rejected_once_an_allow_list_is_set() {
+        assert!(!is_allowed(None, &["image/png".to_string()], &[]));
+    }
+
+    #[test]
+    fn allow_list_rejects_types_not_on_it() {
+        let allowed = vec!["image/png".to_string()];
+        assert!(is_allowed(Some("image/png"), &allowed, &[]));
+        assert!(!is_allowed(Some("image/jpeg"), &allowed, &[]));
+    }
+
+    #[test]
+    fn deny_list_wins_even_if_also_allowed() {
+        let allowed = vec!["image/png".to_string()];
+        let denied = vec!["image/png".to_string()];
+        assert!(!is_allowed(Some("image/png"), &allowed, &denied));
+    }
+
+    #[test]
+    fn extension_matches_accepts_agreeing_extensions() {
+        assert!(extension_matches("photo.png", "image/png"));
+        assert!(extension_matches("photo.JPG", "image/jpeg"));
+        assert!(extension_matches("doc.pdf", "application/pdf"));
+        assert!(extension_matches("archive.zip", "application/zip"));
+    }
+
+    #[test]
+    fn extension_matches_rejects_disagreeing_extensions() {
+        assert!(!extension_matches("photo.png", "image/jpeg"));
+    }
+
+    #[test]
+    fn extension_matches_allows_a_missing_extension() {
+        assert!(extension_matches("README", "image/png"));
+    }
+}